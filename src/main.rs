@@ -1,66 +1,391 @@
+use std::collections::HashMap;
 use std::env;
+use std::fs::{self, File, OpenOptions};
 use std::io::{self, stdin, stdout, Write};
+use std::iter::Peekable;
 use std::process::{exit, Child, Command, Stdio};
+use std::str::Chars;
 struct Config {
     prompt: String,
     version: String,
+    aliases: HashMap<String, String>,
+}
+
+// everything the shell needs to carry between prompts: the static config,
+// the variable table `$VAR` expansion reads from, and the exit status of
+// the last pipeline so `$?` can reflect it.
+struct Shell {
+    config: Config,
+    vars: HashMap<String, String>,
+    last_status: i32,
+}
+
+impl Shell {
+    fn new() -> Self {
+        let mut vars = HashMap::new();
+        // seed with the usual suspects so scripts relying on $PWD/$HOME/$USER work out of the box.
+        for name in ["PWD", "HOME", "USER"] {
+            if let Ok(value) = env::var(name) {
+                vars.insert(name.to_string(), value);
+            }
+        }
+        Shell { config: load_config(), vars, last_status: 0 }
+    }
+}
+
+// expand a `$NAME`, `${NAME}` or `$?` that begins right after the `$` the
+// caller already consumed. an undefined variable expands to the empty
+// string rather than being left as-is.
+fn expand_variable(chars: &mut Peekable<Chars>, shell: &Shell) -> String {
+    match chars.peek() {
+        Some('?') => {
+            chars.next();
+            shell.last_status.to_string()
+        }
+        Some('{') => {
+            chars.next();
+            let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+            shell.vars.get(&name).cloned().unwrap_or_default()
+        }
+        Some(&c) if c.is_alphabetic() || c == '_' => {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    name.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            shell.vars.get(&name).cloned().unwrap_or_default()
+        }
+        _ => "$".to_string(),
+    }
+}
+
+// tokenize a full line into pipeline segments, each a Vec of
+// (token, was_quoted) pairs. quote parsing happens here, before any
+// splitting on `|`, so a `|` (or redirection char) inside quotes is just
+// text: single quotes are fully literal, double quotes still expand
+// `$VAR`, and a backslash escapes the next character (space, quote, `|`,
+// `>`) in either context. `was_quoted` is true for any token that went
+// through a quote or an escape, so callers can tell a real `>` operator
+// apart from a quoted/escaped `">"` argument that merely looks like one.
+fn lex_pipeline(buffer: &str, shell: &Shell) -> Result<Vec<Vec<(String, bool)>>, String> {
+    let mut segments: Vec<Vec<(String, bool)>> = vec![Vec::new()];
+    let mut current = String::new();
+    let mut token_active = false;
+    let mut token_quoted = false;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut chars = buffer.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_single {
+            if c == '\'' {
+                in_single = false;
+            } else {
+                current.push(c);
+            }
+            continue;
+        }
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                current.push(escaped);
+                token_active = true;
+                token_quoted = true;
+            }
+            continue;
+        }
+        if in_double {
+            match c {
+                '"' => in_double = false,
+                '$' => {
+                    current.push_str(&expand_variable(&mut chars, shell));
+                }
+                _ => current.push(c),
+            }
+            continue;
+        }
+        match c {
+            '\'' => {
+                in_single = true;
+                token_active = true;
+                token_quoted = true;
+            }
+            '"' => {
+                in_double = true;
+                token_active = true;
+                token_quoted = true;
+            }
+            '$' => {
+                current.push_str(&expand_variable(&mut chars, shell));
+                token_active = true;
+            }
+            '|' => {
+                if token_active || !current.is_empty() {
+                    let token = std::mem::take(&mut current);
+                    segments.last_mut().unwrap().push((token, token_quoted));
+                    token_active = false;
+                    token_quoted = false;
+                }
+                segments.push(Vec::new());
+            }
+            c if c.is_whitespace() => {
+                if token_active || !current.is_empty() {
+                    let token = std::mem::take(&mut current);
+                    segments.last_mut().unwrap().push((token, token_quoted));
+                    token_active = false;
+                    token_quoted = false;
+                }
+            }
+            _ => {
+                current.push(c);
+                token_active = true;
+            }
+        }
+    }
+
+    if in_single || in_double {
+        return Err("rusty-shell: unterminated quote".to_string());
+    }
+    if token_active || !current.is_empty() {
+        segments.last_mut().unwrap().push((current, token_quoted));
+    }
+    Ok(segments)
+}
+
+// where a command's stdin/stdout/stderr should go once redirection
+// tokens have been stripped out of its argument list.
+#[derive(Default)]
+struct Redirections {
+    stdin_file: Option<String>,
+    stdout_file: Option<String>,
+    stdout_append: bool,
+    stderr_file: Option<String>,
+}
+
+// scan a command's already-whitespace-split tokens for `>`, `>>`, `<` and
+// `2>`, removing them (and the filename that follows) from the argument
+// list and recording where they point to. only an unquoted/unescaped
+// token counts as a real operator, so `cat ">" x` or `cat \> x` pass
+// `>` through as a literal argument instead of redirecting.
+fn extract_redirections(tokens: Vec<(String, bool)>) -> (Vec<String>, Redirections) {
+    let mut args = Vec::with_capacity(tokens.len());
+    let mut redirections = Redirections::default();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some((token, quoted)) = tokens.next() {
+        match token.as_str() {
+            ">" if !quoted => redirections.stdout_file = tokens.next().map(|(t, _)| t),
+            ">>" if !quoted => {
+                redirections.stdout_file = tokens.next().map(|(t, _)| t);
+                redirections.stdout_append = true;
+            }
+            "<" if !quoted => redirections.stdin_file = tokens.next().map(|(t, _)| t),
+            "2>" if !quoted => redirections.stderr_file = tokens.next().map(|(t, _)| t),
+            _ => args.push(token),
+        }
+    }
+    (args, redirections)
 }
 
 impl Default for Config {
     fn default() -> Self {
-        Config { prompt: ">".into(), version: "0.1".into() }
+        Config { prompt: ">".into(), version: "0.1".into(), aliases: HashMap::new() }
     }
 }
 
+// read `~/.rustyshellrc` (a small TOML file) and layer its `prompt`/`version`
+// and `[aliases]` table over the defaults. a missing or malformed file just
+// means we keep the defaults, it's not an error.
+fn load_config() -> Config {
+    let mut config = Config::default();
+    let home = env::var("HOME").unwrap_or_else(|_| "/".into());
+    let contents = match fs::read_to_string(format!("{}/.rustyshellrc", home)) {
+        Ok(contents) => contents,
+        Err(_) => return config,
+    };
+
+    let mut in_aliases = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            in_aliases = line.trim_start_matches('[').trim_end_matches(']') == "aliases";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim().trim_matches('"').to_string();
+        if in_aliases {
+            config.aliases.insert(key.to_string(), value);
+        } else {
+            match key {
+                "prompt" => config.prompt = value,
+                "version" => config.version = value,
+                _ => {}
+            }
+        }
+    }
+    config
+}
+
 fn main() {
-    // create a config structure from default implementation
-    let config = Config::default();
+    // create the shell state (config + variable table) from default implementation
+    let mut shell = Shell::new();
+
+    // a path on argv means "run this script non-interactively", not "start a REPL".
+    if let Some(path) = env::args().nth(1) {
+        if let Err(e) = run_script_file(&path, &mut shell) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        exit(shell.last_status);
+    }
+
     //loop indefinitely waiting for user input through stdin
     loop {
-        if let Err(e) = prompter(&config) {
+        if let Err(e) = prompter(&mut shell) {
             eprintln!("{}", e);
             return;
         }
     }
 }
 
-// get user input through stdin and parse it 
-fn prompter(config: &Config) -> io::Result<()> {
-    print!("{} ", config.prompt);
+// feed a script file through parse_stdin one line at a time, same as if it had been typed in.
+fn run_script_file(path: &str, shell: &mut Shell) -> io::Result<()> {
+    let contents = fs::read_to_string(path)?;
+    for line in contents.lines() {
+        parse_stdin(line, shell);
+    }
+    Ok(())
+}
+
+// get user input through stdin and parse it
+fn prompter(shell: &mut Shell) -> io::Result<()> {
+    print!("{} ", shell.config.prompt);
     stdout().flush().expect("Could not flush stdout."); // for printing the prompt immediately.
 
     let mut buffer = String::new();
-    stdin().read_line(&mut buffer)?;
-    parse_stdin(&buffer, config);
+    if stdin().read_line(&mut buffer)? == 0 {
+        exit(shell.last_status); // stdin closed (e.g. piped input ran out)
+    }
+    if buffer.trim() == "script" {
+        run_script_block(shell)?;
+    } else {
+        parse_stdin(&buffer, shell);
+    }
+    Ok(())
+}
+
+// `script` ... `end`: collect lines interactively until the `end` terminator
+// and then run them sequentially as a batch, like a REPL-typed script file.
+fn run_script_block(shell: &mut Shell) -> io::Result<()> {
+    let mut lines = Vec::new();
+    loop {
+        print!("script> ");
+        stdout().flush().expect("Could not flush stdout.");
+
+        let mut line = String::new();
+        if stdin().read_line(&mut line)? == 0 || line.trim() == "end" {
+            break;
+        }
+        lines.push(line);
+    }
+    for line in &lines {
+        parse_stdin(line, shell);
+    }
     Ok(())
 }
 
-fn parse_stdin(buffer: &str, config: &Config) {
-    // parse the buffer, split for each pipe
-    let mut commands = buffer.trim().split('|').peekable();
-    // create a variable for previous command, 
+fn parse_stdin(buffer: &str, shell: &mut Shell) {
+    // tokenize the whole line into pipeline segments, honoring quotes and
+    // escapes, and expanding $VAR along the way, before we ever split on `|`.
+    let mut commands = match lex_pipeline(buffer, shell) {
+        Ok(segments) => segments.into_iter().peekable(),
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    // create a variable for previous command,
     // if there is one we will connect previous
-    // command's stdout and current command's stdin 
+    // command's stdout and current command's stdin
     let mut previous_command: Option<Child> = None;
     // while there are still commands, consume each command one by one
-    while let Some(command) = commands.next() {
-        // parse each command, first argument is the program itself, the rest is arguments.
-        let mut args = command.split_whitespace();
+    while let Some(mut tokens) = commands.next() {
         // if user just presses enter, just skip to the next iteration
-        let program = args.next().unwrap_or("skip");
+        if tokens.is_empty() {
+            return;
+        }
+        let mut program = tokens.remove(0).0;
+        // if the program name is a known alias, splice its tokens in ahead of the
+        // remaining args, e.g. `ll` with `ll = "ls -l"` becomes `ls -l` + whatever followed.
+        if let Some(alias) = shell.config.aliases.get(&program) {
+            // words coming from the alias definition itself are bare, never quoted
+            let mut expanded: Vec<(String, bool)> = alias
+                .split_whitespace()
+                .map(|t| (t.to_string(), false))
+                .collect();
+            if !expanded.is_empty() {
+                program = expanded.remove(0).0;
+                expanded.extend(tokens);
+                tokens = expanded;
+            }
+        }
+        // a builtin can't wire a pipe or a file the way a spawned Command can (it has
+        // no Child/Stdio handles of its own), so echo only takes the builtin fast path
+        // when neither applies; otherwise it falls through to the real `echo` binary.
+        // reuse extract_redirections itself (rather than a second ad hoc scan) so the
+        // two can't disagree on what counts as a real (unquoted) redirect operator.
+        let (_, probed_redirections) = extract_redirections(tokens.clone());
+        let echo_needs_external = probed_redirections.stdin_file.is_some()
+            || probed_redirections.stdout_file.is_some()
+            || probed_redirections.stderr_file.is_some()
+            || commands.peek().is_some();
+        // kept as (token, was_quoted) until the generic command branch, which needs
+        // the quoting info to run extract_redirections; builtins that only care about
+        // the text strip the flag off as they consume it.
+        let mut args = tokens.into_iter();
         // check for special programs
-        match program {
+        match program.as_str() {
             // skip an iteration and print the prompt again
             "skip" => {
                 return;
             }
             //exit the shell
             "exit" => {
-                exit(0);
+                let status = args
+                    .next()
+                    .and_then(|(code, _)| code.parse().ok())
+                    .unwrap_or(0);
+                exit(status);
             }
             //print version
             "version" => {
-                println!("{}",config.version);
+                println!("{}", shell.config.version);
+            }
+            // assign a shell variable: `set NAME=value`
+            "set" => {
+                for (assignment, _) in args.collect::<Vec<_>>() {
+                    if let Some((name, value)) = assignment.split_once('=') {
+                        shell.vars.insert(name.to_string(), value.to_string());
+                    } else {
+                        eprintln!("set: expected NAME=value, got '{}'", assignment);
+                    }
+                }
+                previous_command = None;
+            }
+            // echo is a builtin so it prints the already-expanded args directly,
+            // as long as there's no redirection or pipe for it to honor.
+            "echo" if !echo_needs_external => {
+                let words: Vec<String> = args.map(|(t, _)| t).collect();
+                println!("{}", words.join(" "));
+                previous_command = None;
             }
             //change current shell, cd should be shell built-in
             // because it changes internals of the shell,
@@ -69,34 +394,84 @@ fn parse_stdin(buffer: &str, config: &Config) {
             // also: https://unix.stackexchange.com/questions/38808/why-is-cd-not-a-program/38819#38819
             "cd" => {
                 // check if there is a home directory, if not use the root directory as home
-                let home = env::var("HOME").unwrap_or("/".into());
-                let new_dir = args.next().unwrap_or(&home);
-                if let Err(e) = env::set_current_dir(new_dir) {
+                let home = shell.vars.get("HOME").cloned().unwrap_or("/".into());
+                let new_dir = args.next().map_or(home, |(t, _)| t);
+                if let Err(e) = env::set_current_dir(&new_dir) {
                     eprintln!("{}", e);
+                } else if let Ok(cwd) = env::current_dir() {
+                    // keep $PWD in sync with the shell's actual working directory
+                    shell.vars.insert("PWD".into(), cwd.display().to_string());
                 }
                 // cd doesn't accept arguments through stdin, therefore we don't care about the previous command
                 // and we make it None because cd won't pipe anything to stdout.
                 previous_command = None;
             }
             //usual programs,
-            program => {
+            _ => {
+                let (args, redirections) = extract_redirections(args.collect());
+
                 // get stdin through pipe if there was a previous command, else inherit from parent.
-                let input = previous_command.map_or(Stdio::inherit(), |output| {
-                    Stdio::from(output.stdout.unwrap())
-                });
-                // if there is another command next, we connect our stdout to their stdin
-                let output = if commands.peek().is_some() {
+                // a `<` file always wins, but a `<` in the middle of a pipeline makes no sense
+                // since the previous command is already feeding this one's stdin.
+                let input = if let Some(file) = &redirections.stdin_file {
+                    if previous_command.is_some() {
+                        eprintln!("rusty-shell: ambiguous input redirect in pipeline");
+                        return;
+                    }
+                    match File::open(file) {
+                        Ok(f) => Stdio::from(f),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return;
+                        }
+                    }
+                } else {
+                    // the previous command may have redirected its own stdout to a file
+                    // instead of piping it (no `Stdio::piped()`), in which case there's
+                    // nothing to wire up and we fall back to inheriting stdin.
+                    previous_command.map_or(Stdio::inherit(), |output| {
+                        output.stdout.map_or(Stdio::inherit(), Stdio::from)
+                    })
+                };
+                // if there is another command next, we connect our stdout to their stdin,
+                // unless this command redirects its own stdout to a file.
+                let output = if let Some(file) = &redirections.stdout_file {
+                    let opened = if redirections.stdout_append {
+                        OpenOptions::new().append(true).create(true).open(file)
+                    } else {
+                        File::create(file)
+                    };
+                    match opened {
+                        Ok(f) => Stdio::from(f),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return;
+                        }
+                    }
+                } else if commands.peek().is_some() {
                     //there is another command
                     Stdio::piped()
                 } else {
                     //we are the final command
                     Stdio::inherit()
                 };
+                // `2>` always overrides stderr, pipelines never wire stderr between commands.
+                let error = match &redirections.stderr_file {
+                    Some(file) => match File::create(file) {
+                        Ok(f) => Stdio::from(f),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return;
+                        }
+                    },
+                    None => Stdio::inherit(),
+                };
                 //run the command with specified configuration
                 let output = Command::new(program)
                     .args(args)
                     .stdin(input)
                     .stdout(output)
+                    .stderr(error)
                     .spawn();
                 // save the current command and go to the next command in buffer, 
                 // this helps us when checking the final command because we will have to wait
@@ -107,6 +482,9 @@ fn parse_stdin(buffer: &str, config: &Config) {
                     }
                     Err(e) => {
                         previous_command = None; // there was an error, don't save the commands
+                        // spawn failed (e.g. command not found), so there's no exit status to
+                        // wait() on later: set $? here instead of leaving it stale.
+                        shell.last_status = 127;
                         eprintln!("{}", e);
                     }
                 }
@@ -118,11 +496,13 @@ fn parse_stdin(buffer: &str, config: &Config) {
         // check exit status
         match last_command.wait() {
             Ok(exit_status) => {
+                // record the status so the next line's $? sees it
+                shell.last_status = exit_status.code().unwrap_or(1);
                 // check if there was an error.
                 if !exit_status.success() {
                     // command ran but there was an error
                     // default to 1 if there was no error status
-                    eprint!("{} ", exit_status.code().unwrap_or(1));
+                    eprint!("{} ", shell.last_status);
                 }
             }
             // command couldn't run